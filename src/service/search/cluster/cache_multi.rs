@@ -16,6 +16,7 @@
 use config::cluster::LOCAL_NODE;
 use infra::errors::{Error, ErrorCodes};
 use proto::cluster_rpc::{self, QueryCacheRequest};
+use sha2::{Digest, Sha256};
 use tonic::{codec::CompressionEncoding, metadata::MetadataValue, transport::Channel, Request};
 use tracing::{info_span, Instrument};
 
@@ -47,8 +48,7 @@ pub async fn get_cached_results(
     let local_node = infra_cluster::get_node_by_uuid(LOCAL_NODE.uuid.as_str()).await;
     nodes.retain(|node| node.is_querier() && !node.uuid.eq(LOCAL_NODE.uuid.as_str()));
 
-    let querier_num = nodes.len();
-    if querier_num == 0 && local_node.is_none() {
+    if nodes.is_empty() && local_node.is_none() {
         log::error!("no querier node online");
         return vec![];
     };
@@ -218,19 +218,84 @@ pub async fn get_cached_results(
     .await;
 
     {
-        results.push((local_node.unwrap(), local_results));
+        // Local goes first so its segments populate `seen_segments` below
+        // before any remote ones: the local copy is the authoritative/
+        // freshest one for this node's own query, so it should win a
+        // same-range divergence rather than whichever remote task happened
+        // to be iterated first.
+        results.insert(0, (local_node.unwrap(), local_results));
     }
 
+    // Nodes can report byte-identical cached segments for the same
+    // `query_key`; keep only the first copy of each (response_start_time,
+    // response_end_time) we see. A second node reporting a *different* hash
+    // for the same range means the segments have silently diverged (or one
+    // is corrupt), so it's logged and dropped instead of merged. This is a
+    // local, post-fetch dedup over payloads every node already shipped in
+    // full -- skipping the transfer itself for segments we've already seen
+    // would need a hash-summary round trip added to `QueryCacheRequest` and
+    // its reply, which isn't implemented here.
+    let mut seen_segments: std::collections::HashMap<(i64, i64), [u8; 32]> =
+        std::collections::HashMap::new();
     let mut all_results = Vec::new();
     for (_, res) in results {
-        all_results.extend(res);
+        for item in res {
+            let hash = segment_hash(&query_key, &item);
+            let range = (item.response_start_time, item.response_end_time);
+            match seen_segments.get(&range) {
+                Some(prev_hash) if *prev_hash == hash => continue,
+                Some(_) => {
+                    log::warn!(
+                        "[trace_id {trace_id}] get_cached_results: divergent cached segment for query_key {query_key} range [{}, {}), dropping",
+                        range.0, range.1
+                    );
+                    continue;
+                }
+                None => {
+                    seen_segments.insert(range, hash);
+                }
+            }
+            all_results.push(item);
+        }
     }
+
     let mut results = Vec::new();
-    recursive_process_muliple_metas(&all_results, cache_req.clone(), &mut results);
+    select_cache_segments_covering_range(&all_results, cache_req.clone(), &mut results);
     results
 }
 
-fn recursive_process_muliple_metas(
+/// `SHA-256` over the `query_key`, a cached segment's time range, and its
+/// serialized payload. Used to tell a duplicate segment (same hash) apart
+/// from a diverged/corrupt one (different hash, same range) reported by
+/// another node.
+fn segment_hash(query_key: &str, res: &CachedQueryResponse) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(query_key.as_bytes());
+    hasher.update(res.response_start_time.to_be_bytes());
+    hasher.update(res.response_end_time.to_be_bytes());
+    if let Ok(bytes) = serde_json::to_vec(&res.cached_response) {
+        hasher.update(&bytes);
+    }
+    hasher.finalize().into()
+}
+
+/// Picks the minimum set of `cache_metas` needed to cover `[q_start_time,
+/// q_end_time)` with the fewest and smallest possible gaps.
+///
+/// This is the classic point-cover greedy: clip every meta to the query
+/// range and sort by `response_start_time`, then sweep a cursor forward from
+/// `q_start_time`. At each step, among all metas whose start has already
+/// been reached by the cursor, keep the one reaching the furthest
+/// `response_end_time`, clamp its start to the cursor so it doesn't overlap
+/// whatever was chosen before it, and advance the cursor to its (unclamped)
+/// end; that meta dominates every other reachable one, so none of them can
+/// ever do better. If nothing starts at or before the cursor there's a
+/// genuine gap, which is left for the caller's delta computation to fill
+/// from storage, and the cursor jumps to the next meta's start. This always
+/// finds an optimal, non-overlapping cover in O(n log n), unlike the
+/// previous largest-overlap-first recursion, which could discard a meta
+/// whose tail would have closed a gap.
+fn select_cache_segments_covering_range(
     cache_metas: &[CachedQueryResponse],
     cache_req: CacheQueryRequest,
     results: &mut Vec<CachedQueryResponse>,
@@ -239,36 +304,63 @@ fn recursive_process_muliple_metas(
         return;
     }
 
-    // Filter relevant metas that are within the overall query range
-    let relevant_metas: Vec<_> = cache_metas
+    let mut metas: Vec<_> = cache_metas
         .iter()
         .filter(|m| {
             m.response_start_time <= cache_req.q_end_time
                 && m.response_end_time >= cache_req.q_start_time
         })
         .cloned()
+        .map(|mut m| {
+            // Clip to the query range before the sweep runs, so a segment
+            // that only partially overlaps the query never gets emitted with
+            // a range wider than what was actually asked for.
+            m.response_start_time = m.response_start_time.max(cache_req.q_start_time);
+            m.response_end_time = m.response_end_time.min(cache_req.q_end_time);
+            m
+        })
         .collect();
+    metas.sort_by_key(|m| m.response_start_time);
+
+    let mut chosen = Vec::new();
+    let mut cursor = cache_req.q_start_time;
+    let mut idx = 0;
+    while cursor < cache_req.q_end_time && idx < metas.len() {
+        if metas[idx].response_start_time > cursor {
+            // Gap: nothing covers the cursor yet, jump to the next candidate.
+            cursor = metas[idx].response_start_time;
+            continue;
+        }
 
-    // Sort by start time to process them in sequence
-    let mut sorted_metas = relevant_metas;
-    sorted_metas.sort_by_key(|m| m.response_start_time);
+        // Among all metas reachable from the cursor, take the one with the
+        // furthest-reaching end time.
+        let mut best = idx;
+        let mut next = idx;
+        while next < metas.len() && metas[next].response_start_time <= cursor {
+            if metas[next].response_end_time > metas[best].response_end_time {
+                best = next;
+            }
+            next += 1;
+        }
 
-    // Find the largest overlapping meta within the query time range
-    if let Some(largest_meta) = sorted_metas.clone().iter().max_by_key(|meta| {
-        meta.response_end_time.min(cache_req.q_end_time)
-            - meta.response_start_time.max(cache_req.q_start_time)
-    }) {
-        results.push(largest_meta.clone());
+        if metas[best].response_end_time <= cursor {
+            // Every reachable meta is already covered by what we've chosen;
+            // nothing left to gain from this batch.
+            idx = next;
+            continue;
+        }
 
-        // Filter out the largest meta and call recursively with non-overlapping metas
-        let remaining_metas: Vec<_> = sorted_metas
-            .into_iter()
-            .filter(|meta| {
-                meta.response_end_time <= largest_meta.response_start_time
-                    || meta.response_start_time >= largest_meta.response_end_time
-            })
-            .collect();
+        // Clamp the start to the cursor so this pick never overlaps the
+        // previously chosen segment's tail.
+        let mut picked = metas[best].clone();
+        picked.response_start_time = picked.response_start_time.max(cursor);
+        cursor = metas[best].response_end_time;
+        chosen.push(picked);
+        idx = next;
+    }
 
-        recursive_process_muliple_metas(&remaining_metas, cache_req, results);
+    if cache_req.is_descending {
+        chosen.reverse();
     }
+    results.extend(chosen);
 }